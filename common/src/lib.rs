@@ -1,4 +1,5 @@
-use std::ops::Shl;
+use std::io::{self, Read, Write};
+use std::ops::{Add, Div, Mul, Not, Shl};
 
 use bitcoin_hashes::{sha256d, Hash};
 use serde::{Deserialize, Serialize};
@@ -9,18 +10,89 @@ macro_rules! from_hex {
     };
 }
 
+/// A reference to a specific output of a previous transaction.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+pub struct OutPoint {
+    /// The txid of the referenced transaction.
+    pub txid: [u8; 32],
+    /// The index of the referenced output within that transaction.
+    pub vout: u32,
+}
+
+/// A transaction input, spending a previous output.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+pub struct TxIn {
+    /// The output being spent.
+    pub previous_output: OutPoint,
+    /// The unlocking script.
+    pub script_sig: Vec<u8>,
+    /// The sequence number.
+    pub sequence: u32,
+    /// The witness stack for this input (empty for non-SegWit inputs).
+    pub witness: Vec<Vec<u8>>,
+}
+
+/// A transaction output, locking a number of satoshis to a script.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+pub struct TxOut {
+    /// The value in satoshis.
+    pub value: u64,
+    /// The locking script.
+    pub script_pubkey: Vec<u8>,
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub struct Transaction {
-    data: Vec<u8>,
+    /// Transaction version number.
+    pub version: i32,
+    /// The inputs of the transaction.
+    pub input: Vec<TxIn>,
+    /// The outputs of the transaction.
+    pub output: Vec<TxOut>,
+    /// The block height or timestamp at which this transaction is final.
+    pub lock_time: u32,
 }
 
 impl Transaction {
+    /// The transaction id: the double-SHA256 of the non-witness serialization.
     pub fn txid(&self) -> [u8; 32] {
         let mut serialized = vec![];
-        serialized.extend_from_slice(&self.data);
-        println!("serialized tx: {:x?}", serialized);
+        self.consensus_encode(&mut serialized)
+            .expect("writing to a Vec is infallible");
         sha256d::Hash::hash(&serialized).to_byte_array()
     }
+
+    /// The witness transaction id: the double-SHA256 of the full serialization
+    /// including witness data. Equal to `txid()` for non-SegWit transactions.
+    pub fn wtxid(&self) -> [u8; 32] {
+        let mut serialized = vec![];
+        self.encode_with_witness(&mut serialized)
+            .expect("writing to a Vec is infallible");
+        sha256d::Hash::hash(&serialized).to_byte_array()
+    }
+
+    /// Whether any input carries a witness stack.
+    fn has_witness(&self) -> bool {
+        self.input.iter().any(|txin| !txin.witness.is_empty())
+    }
+
+    /// Encode the full serialization, inserting the SegWit marker, flag and
+    /// witness stacks when any input has a witness.
+    fn encode_with_witness<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+        if !self.has_witness() {
+            return self.consensus_encode(writer);
+        }
+        let mut len = self.version.consensus_encode(writer)?;
+        writer.write_all(&[0x00, 0x01])?;
+        len += 2;
+        len += self.input.consensus_encode(writer)?;
+        len += self.output.consensus_encode(writer)?;
+        for txin in &self.input {
+            len += txin.witness.consensus_encode(writer)?;
+        }
+        len += self.lock_time.consensus_encode(writer)?;
+        Ok(len)
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
@@ -41,6 +113,74 @@ impl Block {
     pub fn calculate_block_hash(&self) -> [u8; 32] {
         self.header.calculate_hash()
     }
+
+    /// Calculate the witness merkle root of the block.
+    ///
+    /// Identical to [`calculate_merkle_root`] but over wtxids, with the
+    /// coinbase's wtxid forced to all-zero bytes as required by BIP141.
+    pub fn calculate_witness_root(&self) -> [u8; 32] {
+        let mut hashes: Vec<[u8; 32]> = self.txdata.iter().map(|tx| tx.wtxid()).collect();
+        if let Some(first) = hashes.first_mut() {
+            *first = [0u8; 32];
+        }
+        calculate_merkle_root(hashes)
+    }
+
+    /// Whether the coinbase carries a SegWit commitment output.
+    pub fn has_witness_commitment(&self) -> bool {
+        self.txdata
+            .first()
+            .is_some_and(|coinbase| coinbase.output.iter().any(is_witness_commitment))
+    }
+
+    /// Validate the SegWit commitment in the coinbase transaction.
+    ///
+    /// The last coinbase output whose scriptPubKey begins with the 6-byte
+    /// marker `6a24aa21a9ed` must commit to `SHA256d(witness_root || reserved)`,
+    /// where `reserved` is the single 32-byte witness item of the coinbase
+    /// input. Returns `false` if the commitment is absent or does not match.
+    pub fn validate_witness_commitment(&self) -> bool {
+        let coinbase = match self.txdata.first() {
+            Some(tx) => tx,
+            None => return false,
+        };
+
+        // The reserved value is the coinbase input's single 32-byte witness item.
+        let reserved = match coinbase.input.first() {
+            Some(txin) if txin.witness.len() == 1 && txin.witness[0].len() == 32 => {
+                &txin.witness[0]
+            }
+            _ => return false,
+        };
+
+        // Scan for the last output carrying the commitment marker.
+        let commitment = coinbase.output.iter().rev().find_map(|out| {
+            if is_witness_commitment(out) {
+                Some(&out.script_pubkey[6..38])
+            } else {
+                None
+            }
+        });
+        let commitment = match commitment {
+            Some(c) => c,
+            None => return false,
+        };
+
+        let witness_root = self.calculate_witness_root();
+        let mut preimage = Vec::with_capacity(64);
+        preimage.extend_from_slice(&witness_root);
+        preimage.extend_from_slice(reserved);
+        let expected = sha256d::Hash::hash(&preimage).to_byte_array();
+
+        expected.as_slice() == commitment
+    }
+}
+
+/// Whether an output is a SegWit commitment: an `OP_RETURN` pushing 36 bytes
+/// prefixed with the `aa21a9ed` commitment header.
+fn is_witness_commitment(out: &TxOut) -> bool {
+    const MARKER: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+    out.script_pubkey.len() >= 38 && out.script_pubkey[..6] == MARKER
 }
 
 /// Calculate the Merkle root from a list of transaction hashes.
@@ -77,6 +217,110 @@ fn calculate_merkle_root(mut hashes: Vec<[u8; 32]>) -> [u8; 32] {
     hashes[0]
 }
 
+/// A BIP37-style partial merkle tree, proving that a set of transactions is
+/// included in a block without shipping every transaction.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// The total number of transactions in the block.
+    pub num_transactions: u32,
+    /// The hashes needed to reconstruct the tree, in depth-first order.
+    pub hashes: Vec<[u8; 32]>,
+    /// One flag bit per traversed node, in depth-first order.
+    pub flag_bits: Vec<bool>,
+}
+
+impl MerkleProof {
+    /// Reconstruct the merkle root from the partial tree.
+    ///
+    /// Returns the reconstructed root and the matched txids, or `None` if the
+    /// proof is malformed (hashes or flag bits left unconsumed, or the width
+    /// implied by `num_transactions` is exceeded).
+    pub fn verify(&self) -> Option<([u8; 32], Vec<[u8; 32]>)> {
+        if self.num_transactions == 0 {
+            return None;
+        }
+        let height = tree_height(self.num_transactions);
+        let mut cursor = ProofCursor {
+            proof: self,
+            bits_used: 0,
+            hashes_used: 0,
+            matched: Vec::new(),
+        };
+        let root = cursor.walk(height, 0)?;
+        // Every hash and flag bit must be consumed exactly.
+        if cursor.hashes_used != self.hashes.len() || cursor.bits_used != self.flag_bits.len() {
+            return None;
+        }
+        Some((root, cursor.matched))
+    }
+}
+
+/// Height of the merkle tree, `ceil(log2(num_transactions))`.
+///
+/// Capped at 32 so an attacker-supplied `num_transactions > 2^31` cannot push
+/// the `1 << height` shift past the width of a `u32`.
+fn tree_height(num_transactions: u32) -> u32 {
+    let mut height = 0;
+    while height < 32 && (1u32 << height) < num_transactions {
+        height += 1;
+    }
+    height
+}
+
+/// Number of nodes at the given `height` (height 0 is the transaction leaves).
+fn calc_tree_width(num_transactions: u32, height: u32) -> u32 {
+    // Compute in `u64` so a `num_transactions` near `u32::MAX` can't overflow
+    // the `+ (1 << height) - 1` rounding for adversarial input.
+    (((num_transactions as u64) + (1u64 << height) - 1) >> height) as u32
+}
+
+/// Depth-first traversal state over a [`MerkleProof`].
+struct ProofCursor<'a> {
+    proof: &'a MerkleProof,
+    bits_used: usize,
+    hashes_used: usize,
+    matched: Vec<[u8; 32]>,
+}
+
+impl ProofCursor<'_> {
+    fn next_bit(&mut self) -> Option<bool> {
+        let bit = *self.proof.flag_bits.get(self.bits_used)?;
+        self.bits_used += 1;
+        Some(bit)
+    }
+
+    fn next_hash(&mut self) -> Option<[u8; 32]> {
+        let hash = *self.proof.hashes.get(self.hashes_used)?;
+        self.hashes_used += 1;
+        Some(hash)
+    }
+
+    fn walk(&mut self, height: u32, pos: u32) -> Option<[u8; 32]> {
+        let flag = self.next_bit()?;
+        if height == 0 || !flag {
+            // A leaf, or an unmatched subtree: its hash is supplied directly.
+            let hash = self.next_hash()?;
+            if height == 0 && flag {
+                self.matched.push(hash);
+            }
+            Some(hash)
+        } else {
+            let left = self.walk(height - 1, pos * 2)?;
+            // Duplicate the left child when the right child is absent, matching
+            // the odd-count rule in `calculate_merkle_root`.
+            let right = if pos * 2 + 1 < calc_tree_width(self.proof.num_transactions, height - 1) {
+                self.walk(height - 1, pos * 2 + 1)?
+            } else {
+                left
+            };
+            let mut combined = Vec::with_capacity(64);
+            combined.extend_from_slice(&left);
+            combined.extend_from_slice(&right);
+            Some(sha256d::Hash::hash(&combined).to_byte_array())
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Header {
     pub version: i32,
@@ -87,10 +331,54 @@ pub struct Header {
     pub nonce: u32,
 }
 
-pub fn create_genesis_block() -> Block {
-    let header = create_genesis_block_header();
-    let tx = hex::decode("0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000").unwrap();
-    let txdata = vec![Transaction { data: tx }];
+/// The Bitcoin network a block belongs to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Regtest,
+    Signet,
+}
+
+/// The consensus parameters that vary between networks.
+#[derive(Clone, Debug)]
+pub struct Params {
+    /// The network these parameters describe.
+    pub network: Network,
+    /// The highest permissible target (lowest difficulty).
+    pub max_target: U256,
+    /// The proof-of-work limit as a compact `bits` value.
+    pub pow_limit_bits: u32,
+    /// Whether the minimum-difficulty (testnet 20-minute) rule applies.
+    pub allow_min_difficulty_blocks: bool,
+    /// The genesis block of this network.
+    pub genesis_block: Block,
+}
+
+impl Params {
+    /// Build the consensus parameters for `network`.
+    pub fn new(network: Network) -> Params {
+        let pow_limit_bits = match network {
+            Network::Mainnet | Network::Testnet => 0x1d00ffff,
+            Network::Regtest => 0x207fffff,
+            Network::Signet => 0x1e0377ae,
+        };
+        Params {
+            network,
+            max_target: U256::from_compact(pow_limit_bits),
+            pow_limit_bits,
+            allow_min_difficulty_blocks: matches!(network, Network::Testnet | Network::Regtest),
+            genesis_block: create_genesis_block(network),
+        }
+    }
+}
+
+pub fn create_genesis_block(network: Network) -> Block {
+    let header = create_genesis_block_header(network);
+    // The coinbase transaction (the Times headline) is identical on every network.
+    let bytes = hex::decode("01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000").unwrap();
+    let tx = Transaction::consensus_decode(&mut bytes.as_slice()).unwrap();
+    let txdata = vec![tx];
     Block { header, txdata }
 }
 
@@ -105,72 +393,58 @@ pub fn create_block_1() -> Block {
         bits: 0x1d00ffff,
         nonce: 0x7c2bac1d,
     };
-    let data = from_hex!("01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704ffff001d0104ffffffff0100f2052a0100000043410496b538e853519c726a2c91e61ec11600ae1390813a627c66fb8be7947be63c52da7589379515d4e0a604f8141781e62294721166bf621e73a82cbf2342c858eeac00000000");
-    let txdata = vec![Transaction { data }];
+    let bytes = hex::decode("01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704ffff001d0104ffffffff0100f2052a0100000043410496b538e853519c726a2c91e61ec11600ae1390813a627c66fb8be7947be63c52da7589379515d4e0a604f8141781e62294721166bf621e73a82cbf2342c858eeac00000000").unwrap();
+    let tx = Transaction::consensus_decode(&mut bytes.as_slice()).unwrap();
+    let txdata = vec![tx];
 
     Block { header, txdata }
 }
 
-/// Create the genesis block header for the Bitcoin blockchain.
-pub fn create_genesis_block_header() -> Header {
+/// Create the genesis block header for the given network.
+pub fn create_genesis_block_header(network: Network) -> Header {
+    // The coinbase is identical across networks, so the merkle root is too.
     let merkle_root = from_hex!("3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a");
 
+    // Only the time, bits and nonce differ between networks.
+    let (time, bits, nonce) = match network {
+        Network::Mainnet => (0x495fab29, 0x1d00ffff, 0x7c2bac1d),
+        Network::Testnet => (1296688602, 0x1d00ffff, 414098458),
+        Network::Regtest => (1296688602, 0x207fffff, 2),
+        Network::Signet => (1598918400, 0x1e0377ae, 52613770),
+    };
+
     Header {
         version: 0x01,
         prev_blockhash: [0u8; 32],
         merkle_root,
-        time: 0x495fab29,
-        bits: 0x1d00ffff,
-        nonce: 0x7c2bac1d,
+        time,
+        bits,
+        nonce,
     }
 }
 
 impl Header {
-    fn serialize_block_header(&self) -> Vec<u8> {
-        let mut result = vec![];
-        result.extend_from_slice(&self.version.to_le_bytes());
-        result.extend_from_slice(&self.prev_blockhash);
-        result.extend_from_slice(&self.merkle_root);
-        result.extend_from_slice(&self.time.to_le_bytes());
-        result.extend_from_slice(&self.bits.to_le_bytes());
-        result.extend_from_slice(&self.nonce.to_le_bytes());
-        result
-    }
-
     /// calculate the double SHA-256 hash of a block header
     pub fn calculate_hash(&self) -> [u8; 32] {
-        let serialized = self.serialize_block_header();
+        let mut serialized = vec![];
+        self.consensus_encode(&mut serialized)
+            .expect("writing to a Vec is infallible");
         sha256d::Hash::hash(&serialized).to_byte_array()
     }
 
     /// Extract the target from the bits field of the block header
     fn target(&self) -> U256 {
-        let bits = self.bits;
-        // This is a floating-point "compact" encoding originally used by
-        // OpenSSL, which satoshi put into consensus code, so we're stuck
-        // with it. The exponent needs to have 3 subtracted from it, hence
-        // this goofy decoding code. 3 is due to 3 bytes in the mantissa.
-        let (mant, expt) = {
-            let unshifted_expt = bits >> 24;
-            if unshifted_expt <= 3 {
-                ((bits & 0xFFFFFF) >> (8 * (3 - unshifted_expt as usize)), 0)
-            } else {
-                (bits & 0xFFFFFF, 8 * ((bits >> 24) - 3))
-            }
-        };
-
-        // The mantissa is signed but may not be negative.
-        if mant > 0x7F_FFFF {
-            U256::ZERO
-        } else {
-            U256::from(mant) << expt
-        }
+        U256::from_compact(self.bits)
     }
 
-    /// Validate the proof of work by checking if the block hash is less than or equal to the target.
-    pub fn validate_target(&self) -> bool {
-        let block_hash = self.calculate_hash();
+    /// Validate the proof of work by checking if the block hash is less than or
+    /// equal to the target, rejecting any target above the network maximum.
+    pub fn validate_target(&self, params: &Params) -> bool {
         let target = self.target();
+        if target > params.max_target {
+            return false;
+        }
+        let block_hash = self.calculate_hash();
         // println!("required_target: {:x?}", required_target);
         // println!("Target:          {:x?}", target);
         // println!("Block hash:      {:x?}", block_hash);
@@ -183,6 +457,120 @@ impl Header {
         // Compare the block hash with the target using lexicographical comparison
         hash <= target
     }
+
+    /// The expected number of hashes needed to find a block at this difficulty,
+    /// `floor(2^256 / (target + 1))`, so chain work can be summed across headers.
+    pub fn work(&self) -> U256 {
+        let target = self.target();
+        // 2^256 / (target + 1) == (!target) / (target + 1) + 1
+        (!target).div(target.wrapping_add(U256::ONE)) + U256::ONE
+    }
+}
+
+/// The difficulty retargeting interval, in blocks.
+pub const RETARGET_INTERVAL: u32 = 2016;
+/// The ideal timespan of a retargeting window: two weeks, in seconds.
+pub const TARGET_TIMESPAN: u32 = 14 * 24 * 3600;
+
+/// Recompute the target for the next retargeting window.
+///
+/// `new_target = old_target * actual_timespan / target_timespan`, with
+/// `actual_timespan` first clamped to `[target_timespan/4, target_timespan*4]`.
+pub fn retarget(old_target: U256, actual_timespan: u32) -> U256 {
+    let clamped = actual_timespan.clamp(TARGET_TIMESPAN / 4, TARGET_TIMESPAN * 4);
+    old_target.mul_u64(clamped as u64) / U256::from(TARGET_TIMESPAN)
+}
+
+/// Median timestamp of the (up to) eleven headers preceding index `i`.
+fn median_time_past(headers: &[Header], i: usize) -> u32 {
+    let start = i.saturating_sub(11);
+    let mut times: Vec<u32> = headers[start..i].iter().map(|h| h.time).collect();
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+/// The succinct result of verifying a header chain: the endpoints and the
+/// accumulated proof-of-work. `total_work` is a big-endian 256-bit integer.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+pub struct ChainProof {
+    pub first_hash: [u8; 32],
+    pub last_hash: [u8; 32],
+    pub total_work: [u8; 32],
+}
+
+/// Verify a sequence of `N` consecutive block headers starting at
+/// `start_height`.
+///
+/// Every header must meet its own proof-of-work target, each must link to its
+/// predecessor, no timestamp may be earlier than the median of the preceding
+/// blocks, and at every retargeting boundary whose previous 2016-block window
+/// is among `headers` the encoded `bits` must match the value recomputed from
+/// that window. At a boundary fewer than 2016 headers into `headers` no
+/// recomputation is made: its `bits` must equal the previous header's, as
+/// between retargets. Returns the chain endpoints and the summed work, or
+/// `None` if any check fails.
+pub fn verify_header_chain(
+    headers: &[Header],
+    start_height: u32,
+    params: &Params,
+) -> Option<ChainProof> {
+    let first = headers.first()?;
+    let max_target = params.max_target;
+
+    let mut total_work = U256::ZERO;
+    for (i, header) in headers.iter().enumerate() {
+        if !header.validate_target(params) {
+            return None;
+        }
+
+        let height = start_height + i as u32;
+        if height % RETARGET_INTERVAL == 0 && i >= RETARGET_INTERVAL as usize {
+            // The window is the prior 2016 blocks ending at the previous header.
+            let window_first = &headers[i - RETARGET_INTERVAL as usize];
+            let window_last = &headers[i - 1];
+            let actual_timespan = window_last.time.wrapping_sub(window_first.time);
+            let old_target = U256::from_compact(window_last.bits);
+            let mut new_target = retarget(old_target, actual_timespan);
+            if new_target > max_target {
+                new_target = max_target;
+            }
+            if new_target.to_compact() != header.bits {
+                return None;
+            }
+        } else if i > 0 {
+            // Between retargets the difficulty is held constant, except for the
+            // testnet/regtest 20-minute minimum-difficulty rule: a block mined
+            // more than two target spacings after its parent may drop to the
+            // proof-of-work limit.
+            let prev = &headers[i - 1];
+            let min_difficulty =
+                params.allow_min_difficulty_blocks && header.time > prev.time + 20 * 60;
+            if header.bits != prev.bits
+                && !(min_difficulty && header.bits == params.pow_limit_bits)
+            {
+                return None;
+            }
+        }
+
+        if i > 0 {
+            if header.prev_blockhash != headers[i - 1].calculate_hash() {
+                return None;
+            }
+            // Timestamps need only be monotonic enough to be plausible: reject
+            // a header that predates the median of the preceding blocks.
+            if header.time < median_time_past(headers, i) {
+                return None;
+            }
+        }
+
+        total_work = total_work + header.work();
+    }
+
+    Some(ChainProof {
+        first_hash: first.calculate_hash(),
+        last_hash: headers[headers.len() - 1].calculate_hash(),
+        total_work: total_work.to_be_bytes(),
+    })
 }
 
 /// Big-endian 256 bit integer type.
@@ -201,6 +589,8 @@ impl U256 {
         U256(big, little)
     }
 
+    const ONE: U256 = U256(0, 1);
+
     fn wrapping_shl(self, rhs: u32) -> Self {
         let shift = rhs & 0x000000ff;
 
@@ -219,6 +609,186 @@ impl U256 {
         }
         ret
     }
+
+    fn wrapping_shr(self, rhs: u32) -> Self {
+        let shift = rhs & 0x000000ff;
+
+        let mut ret = U256::ZERO;
+        let word_shift = shift >= 128;
+        let bit_shift = shift % 128;
+
+        if word_shift {
+            ret.1 = self.0 >> bit_shift
+        } else {
+            ret.1 = self.1 >> bit_shift;
+            if bit_shift > 0 {
+                ret.1 += self.0.wrapping_shl(128 - bit_shift);
+            }
+            ret.0 = self.0 >> bit_shift;
+        }
+        ret
+    }
+
+    /// Decode a "compact" `bits` value into a target.
+    ///
+    /// This is a floating-point encoding originally used by OpenSSL, which
+    /// satoshi put into consensus code, so we're stuck with it. The exponent
+    /// needs to have 3 subtracted from it, hence this goofy decoding code. 3
+    /// is due to 3 bytes in the mantissa.
+    pub fn from_compact(bits: u32) -> U256 {
+        let (mant, expt) = {
+            let unshifted_expt = bits >> 24;
+            if unshifted_expt <= 3 {
+                ((bits & 0xFFFFFF) >> (8 * (3 - unshifted_expt as usize)), 0)
+            } else {
+                (bits & 0xFFFFFF, 8 * ((bits >> 24) - 3))
+            }
+        };
+
+        // The mantissa is signed but may not be negative.
+        if mant > 0x7F_FFFF {
+            U256::ZERO
+        } else {
+            U256::from(mant) << expt
+        }
+    }
+
+    /// Creates a `U256` from a big-endian array of `u8`s.
+    pub fn from_be_bytes(a: [u8; 32]) -> U256 {
+        let (high, low) = split_in_half(a);
+        U256(u128::from_be_bytes(high), u128::from_be_bytes(low))
+    }
+
+    /// Converts the `U256` into a big-endian array of `u8`s.
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        out[..16].copy_from_slice(&self.0.to_be_bytes());
+        out[16..].copy_from_slice(&self.1.to_be_bytes());
+        out
+    }
+
+    /// Wrapping (modular) addition.
+    pub fn wrapping_add(self, rhs: U256) -> U256 {
+        let (low, carry) = self.1.overflowing_add(rhs.1);
+        let high = self.0.wrapping_add(rhs.0).wrapping_add(carry as u128);
+        U256(high, low)
+    }
+
+    /// Wrapping (modular) subtraction.
+    pub fn wrapping_sub(self, rhs: U256) -> U256 {
+        let (low, borrow) = self.1.overflowing_sub(rhs.1);
+        let high = self.0.wrapping_sub(rhs.0).wrapping_sub(borrow as u128);
+        U256(high, low)
+    }
+
+    /// Multiply by a single `u64`, wrapping on overflow.
+    pub fn mul_u64(self, rhs: u64) -> U256 {
+        self.mul(U256::from(rhs))
+    }
+
+    /// Full 256-bit multiplication, wrapping on overflow.
+    pub fn mul(self, rhs: U256) -> U256 {
+        let a = self.to_limbs();
+        let b = rhs.to_limbs();
+        let mut res = [0u64; 4];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..(4 - i) {
+                let cur =
+                    res[i + j] as u128 + (a[i] as u128) * (b[j] as u128) + carry;
+                res[i + j] = cur as u64;
+                carry = cur >> 64;
+            }
+        }
+        U256::from_limbs(res)
+    }
+
+    /// Binary long division, returning the quotient (`self / rhs`).
+    fn div(self, rhs: U256) -> U256 {
+        assert!(rhs != U256::ZERO, "division by zero");
+        let mut quotient = U256::ZERO;
+        let mut remainder = U256::ZERO;
+        for i in (0..256u32).rev() {
+            remainder = remainder << 1;
+            if self.bit(i) {
+                remainder.1 |= 1;
+            }
+            if remainder >= rhs {
+                remainder = remainder.wrapping_sub(rhs);
+                quotient = quotient.with_bit_set(i);
+            }
+        }
+        quotient
+    }
+
+    /// Returns the `i`th least-significant bit.
+    fn bit(self, i: u32) -> bool {
+        if i < 128 {
+            (self.1 >> i) & 1 == 1
+        } else {
+            (self.0 >> (i - 128)) & 1 == 1
+        }
+    }
+
+    /// Returns `self` with the `i`th least-significant bit set.
+    fn with_bit_set(mut self, i: u32) -> U256 {
+        if i < 128 {
+            self.1 |= 1 << i;
+        } else {
+            self.0 |= 1 << (i - 128);
+        }
+        self
+    }
+
+    /// The number of significant bits (position of the highest set bit).
+    fn bits_len(self) -> u32 {
+        if self.0 != 0 {
+            256 - self.0.leading_zeros()
+        } else {
+            128 - self.1.leading_zeros()
+        }
+    }
+
+    /// The low 32 bits of the value.
+    fn low_u32(self) -> u32 {
+        self.1 as u32
+    }
+
+    /// The four 64-bit limbs, least-significant first.
+    fn to_limbs(self) -> [u64; 4] {
+        [
+            self.1 as u64,
+            (self.1 >> 64) as u64,
+            self.0 as u64,
+            (self.0 >> 64) as u64,
+        ]
+    }
+
+    /// Rebuilds a `U256` from four 64-bit limbs, least-significant first.
+    fn from_limbs(l: [u64; 4]) -> U256 {
+        let low = (l[0] as u128) | ((l[1] as u128) << 64);
+        let high = (l[2] as u128) | ((l[3] as u128) << 64);
+        U256(high, low)
+    }
+
+    /// Encode the value back into the "compact" `bits` representation, the
+    /// inverse of [`Header::target`]. This lets a verifier check that a
+    /// header's `bits` is the canonical encoding of its target.
+    pub fn to_compact(self) -> u32 {
+        let mut size = self.bits_len().div_ceil(8);
+        let mut mantissa = if size <= 3 {
+            self.low_u32() << (8 * (3 - size))
+        } else {
+            self.wrapping_shr(8 * (size - 3)).low_u32() & 0x00FF_FFFF
+        };
+        // The mantissa is signed; if its top bit is set, shift down a byte and
+        // bump the exponent so the sign bit stays clear.
+        if mantissa & 0x0080_0000 != 0 {
+            mantissa >>= 8;
+            size += 1;
+        }
+        mantissa | (size << 24)
+    }
 }
 
 impl Shl<u32> for U256 {
@@ -228,6 +798,34 @@ impl Shl<u32> for U256 {
     }
 }
 
+impl Add for U256 {
+    type Output = Self;
+    fn add(self, rhs: U256) -> U256 {
+        self.wrapping_add(rhs)
+    }
+}
+
+impl Mul for U256 {
+    type Output = Self;
+    fn mul(self, rhs: U256) -> U256 {
+        U256::mul(self, rhs)
+    }
+}
+
+impl Div for U256 {
+    type Output = Self;
+    fn div(self, rhs: U256) -> U256 {
+        U256::div(self, rhs)
+    }
+}
+
+impl Not for U256 {
+    type Output = Self;
+    fn not(self) -> U256 {
+        U256(!self.0, !self.1)
+    }
+}
+
 impl<T: Into<u128>> From<T> for U256 {
     fn from(x: T) -> Self {
         U256(0, x.into())
@@ -245,9 +843,356 @@ fn split_in_half(a: [u8; 32]) -> ([u8; 16], [u8; 16]) {
     (high, low)
 }
 
+// ---------------------------------------------------------------------------
+// Consensus (de)serialization
+//
+// A minimal re-implementation of Bitcoin's consensus wire format, modelled on
+// rust-bitcoin's `consensus::encode`. Integers are little-endian, variable
+// length quantities use the CompactSize ("varint") encoding, and vectors are
+// length-prefixed with a CompactSize count.
+// ---------------------------------------------------------------------------
+
+/// Errors that can occur while decoding a consensus-encoded structure.
+#[derive(Debug)]
+pub enum Error {
+    /// An underlying I/O error (typically an unexpected end of input).
+    Io(io::Error),
+    /// A CompactSize integer was not encoded in its shortest possible form.
+    NonMinimalVarInt,
+    /// A transaction carried an unknown SegWit flag byte (only `0x01` is valid).
+    UnsupportedSegwitFlag(u8),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// A type that can be serialized into the Bitcoin consensus wire format.
+pub trait Encodable {
+    /// Encode `self` into `writer`, returning the number of bytes written.
+    fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error>;
+}
+
+/// A type that can be deserialized from the Bitcoin consensus wire format.
+pub trait Decodable: Sized {
+    /// Decode an instance of `Self` from `reader`.
+    fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error>;
+}
+
+/// Implement `Encodable`/`Decodable` for a fixed-width little-endian integer.
+macro_rules! impl_int {
+    ($ty:ty, $len:expr) => {
+        impl Encodable for $ty {
+            fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+                writer.write_all(&self.to_le_bytes())?;
+                Ok($len)
+            }
+        }
+
+        impl Decodable for $ty {
+            fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error> {
+                let mut buf = [0u8; $len];
+                reader.read_exact(&mut buf)?;
+                Ok(<$ty>::from_le_bytes(buf))
+            }
+        }
+    };
+}
+
+impl_int!(u8, 1);
+impl_int!(u16, 2);
+impl_int!(u32, 4);
+impl_int!(i32, 4);
+impl_int!(u64, 8);
+
+impl Encodable for [u8; 32] {
+    fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+        writer.write_all(self)?;
+        Ok(32)
+    }
+}
+
+impl Decodable for [u8; 32] {
+    fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error> {
+        let mut buf = [0u8; 32];
+        reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// A CompactSize-encoded unsigned integer (Bitcoin's "varint").
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VarInt(pub u64);
+
+impl Encodable for VarInt {
+    fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+        match self.0 {
+            0..=0xFC => {
+                (self.0 as u8).consensus_encode(writer)?;
+                Ok(1)
+            }
+            0xFD..=0xFFFF => {
+                writer.write_all(&[0xFD])?;
+                (self.0 as u16).consensus_encode(writer)?;
+                Ok(3)
+            }
+            0x1_0000..=0xFFFF_FFFF => {
+                writer.write_all(&[0xFE])?;
+                (self.0 as u32).consensus_encode(writer)?;
+                Ok(5)
+            }
+            _ => {
+                writer.write_all(&[0xFF])?;
+                self.0.consensus_encode(writer)?;
+                Ok(9)
+            }
+        }
+    }
+}
+
+impl Decodable for VarInt {
+    fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error> {
+        match u8::consensus_decode(reader)? {
+            0xFF => {
+                let n = u64::consensus_decode(reader)?;
+                // Reject non-minimal encodings to stay consensus-valid.
+                if n < 0x1_0000_0000 {
+                    Err(Error::NonMinimalVarInt)
+                } else {
+                    Ok(VarInt(n))
+                }
+            }
+            0xFE => {
+                let n = u32::consensus_decode(reader)?;
+                if n < 0x1_0000 {
+                    Err(Error::NonMinimalVarInt)
+                } else {
+                    Ok(VarInt(n as u64))
+                }
+            }
+            0xFD => {
+                let n = u16::consensus_decode(reader)?;
+                if n < 0xFD {
+                    Err(Error::NonMinimalVarInt)
+                } else {
+                    Ok(VarInt(n as u64))
+                }
+            }
+            n => Ok(VarInt(n as u64)),
+        }
+    }
+}
+
+impl<T: Encodable> Encodable for Vec<T> {
+    fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+        let mut len = VarInt(self.len() as u64).consensus_encode(writer)?;
+        for item in self {
+            len += item.consensus_encode(writer)?;
+        }
+        Ok(len)
+    }
+}
+
+impl<T: Decodable> Decodable for Vec<T> {
+    fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error> {
+        let count = VarInt::consensus_decode(reader)?.0;
+        // Never pre-allocate from an untrusted count: a crafted CompactSize can
+        // reach `u64::MAX`, and `with_capacity` would abort the process before a
+        // single element is read. Cap the reservation and grow incrementally;
+        // an implausible count simply exhausts the reader and errors out.
+        const MAX_PREALLOC: u64 = 4096;
+        let mut items = Vec::with_capacity(count.min(MAX_PREALLOC) as usize);
+        for _ in 0..count {
+            items.push(T::consensus_decode(reader)?);
+        }
+        Ok(items)
+    }
+}
+
+impl Encodable for OutPoint {
+    fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+        let mut len = self.txid.consensus_encode(writer)?;
+        len += self.vout.consensus_encode(writer)?;
+        Ok(len)
+    }
+}
+
+impl Decodable for OutPoint {
+    fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error> {
+        Ok(OutPoint {
+            txid: Decodable::consensus_decode(reader)?,
+            vout: Decodable::consensus_decode(reader)?,
+        })
+    }
+}
+
+impl Encodable for TxIn {
+    fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+        let mut len = self.previous_output.consensus_encode(writer)?;
+        len += self.script_sig.consensus_encode(writer)?;
+        len += self.sequence.consensus_encode(writer)?;
+        Ok(len)
+    }
+}
+
+impl Decodable for TxIn {
+    fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error> {
+        // Witness stacks live after the outputs in the SegWit serialization, so
+        // they are decoded by `Transaction::consensus_decode`, not here.
+        Ok(TxIn {
+            previous_output: Decodable::consensus_decode(reader)?,
+            script_sig: Decodable::consensus_decode(reader)?,
+            sequence: Decodable::consensus_decode(reader)?,
+            witness: Vec::new(),
+        })
+    }
+}
+
+impl Encodable for TxOut {
+    fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+        let mut len = self.value.consensus_encode(writer)?;
+        len += self.script_pubkey.consensus_encode(writer)?;
+        Ok(len)
+    }
+}
+
+impl Decodable for TxOut {
+    fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error> {
+        Ok(TxOut {
+            value: Decodable::consensus_decode(reader)?,
+            script_pubkey: Decodable::consensus_decode(reader)?,
+        })
+    }
+}
+
+impl Encodable for Transaction {
+    /// Encode the non-witness ("stripped") serialization of the transaction.
+    fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+        let mut len = self.version.consensus_encode(writer)?;
+        len += self.input.consensus_encode(writer)?;
+        len += self.output.consensus_encode(writer)?;
+        len += self.lock_time.consensus_encode(writer)?;
+        Ok(len)
+    }
+}
+
+impl Decodable for Transaction {
+    fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error> {
+        let version = i32::consensus_decode(reader)?;
+        let mut input: Vec<TxIn> = Decodable::consensus_decode(reader)?;
+        let output;
+        if input.is_empty() {
+            // An empty input vector is really the SegWit marker (0x00); the
+            // next byte is the flag, then the real inputs and outputs follow.
+            let flag = u8::consensus_decode(reader)?;
+            if flag != 0x01 {
+                return Err(Error::UnsupportedSegwitFlag(flag));
+            }
+            input = Decodable::consensus_decode(reader)?;
+            output = Decodable::consensus_decode(reader)?;
+            for txin in input.iter_mut() {
+                txin.witness = Decodable::consensus_decode(reader)?;
+            }
+        } else {
+            output = Decodable::consensus_decode(reader)?;
+        }
+        let lock_time = u32::consensus_decode(reader)?;
+        Ok(Transaction {
+            version,
+            input,
+            output,
+            lock_time,
+        })
+    }
+}
+
+impl Encodable for Header {
+    fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+        let mut len = self.version.consensus_encode(writer)?;
+        len += self.prev_blockhash.consensus_encode(writer)?;
+        len += self.merkle_root.consensus_encode(writer)?;
+        len += self.time.consensus_encode(writer)?;
+        len += self.bits.consensus_encode(writer)?;
+        len += self.nonce.consensus_encode(writer)?;
+        Ok(len)
+    }
+}
+
+impl Decodable for Header {
+    fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error> {
+        Ok(Header {
+            version: Decodable::consensus_decode(reader)?,
+            prev_blockhash: Decodable::consensus_decode(reader)?,
+            merkle_root: Decodable::consensus_decode(reader)?,
+            time: Decodable::consensus_decode(reader)?,
+            bits: Decodable::consensus_decode(reader)?,
+            nonce: Decodable::consensus_decode(reader)?,
+        })
+    }
+}
+
+impl Encodable for Block {
+    fn consensus_encode<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
+        let mut len = self.header.consensus_encode(writer)?;
+        len += self.txdata.consensus_encode(writer)?;
+        Ok(len)
+    }
+}
+
+impl Decodable for Block {
+    fn consensus_decode<R: Read + ?Sized>(reader: &mut R) -> Result<Self, Error> {
+        Ok(Block {
+            header: Decodable::consensus_decode(reader)?,
+            txdata: Decodable::consensus_decode(reader)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{calculate_merkle_root, Block, Header, Transaction};
+    use crate::{
+        calculate_merkle_root, Block, Decodable, Encodable, Header, MerkleProof, Network, OutPoint,
+        Params, Transaction, TxIn, TxOut, VarInt,
+    };
+    use bitcoin_hashes::{sha256d, Hash};
+
+    /// Build a coinbase transaction carrying `reserved` as its witness item and
+    /// a commitment output for the given witness root.
+    fn segwit_coinbase(reserved: [u8; 32], witness_root: [u8; 32]) -> Transaction {
+        let mut preimage = vec![];
+        preimage.extend_from_slice(&witness_root);
+        preimage.extend_from_slice(&reserved);
+        let commitment = sha256d::Hash::hash(&preimage).to_byte_array();
+
+        let mut script_pubkey = vec![0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+        script_pubkey.extend_from_slice(&commitment);
+
+        Transaction {
+            version: 1,
+            input: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: [0u8; 32],
+                    vout: 0xffff_ffff,
+                },
+                script_sig: vec![0x51],
+                sequence: 0xffff_ffff,
+                witness: vec![reserved.to_vec()],
+            }],
+            output: vec![
+                TxOut {
+                    value: 5_000_000_000,
+                    script_pubkey: vec![0x51],
+                },
+                TxOut {
+                    value: 0,
+                    script_pubkey,
+                },
+            ],
+            lock_time: 0,
+        }
+    }
 
     #[test]
     fn test_merkle_root() {
@@ -279,7 +1224,7 @@ mod tests {
 
     #[test]
     fn test_genesis() {
-        let header = crate::create_genesis_block_header();
+        let header = crate::create_genesis_block_header(Network::Mainnet);
         let mut hash = header.calculate_hash();
         hash.reverse();
         let expected: [u8; 32] =
@@ -289,7 +1234,227 @@ mod tests {
 
     #[test]
     fn test_target() {
-        let header = crate::create_genesis_block_header();
-        assert!(header.validate_target());
+        let header = crate::create_genesis_block_header(Network::Mainnet);
+        assert!(header.validate_target(&Params::new(Network::Mainnet)));
+    }
+
+    #[test]
+    fn test_varint_round_trip() {
+        for n in [0u64, 0xFC, 0xFD, 0xFFFF, 0x1_0000, 0xFFFF_FFFF, 0x1_0000_0000] {
+            let mut encoded = vec![];
+            VarInt(n).consensus_encode(&mut encoded).unwrap();
+            let decoded = VarInt::consensus_decode(&mut encoded.as_slice()).unwrap();
+            assert_eq!(decoded.0, n);
+        }
+    }
+
+    #[test]
+    fn test_vec_decode_rejects_huge_count() {
+        // A CompactSize of u64::MAX must not trigger a capacity-overflow abort;
+        // decoding fails gracefully once the reader is exhausted.
+        let bytes = [0xFFu8; 9];
+        assert!(Vec::<u32>::consensus_decode(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_non_minimal_varint_rejected() {
+        // 0xFD followed by a value that fits in a single byte is non-minimal.
+        let bytes = [0xFDu8, 0x01, 0x00];
+        assert!(VarInt::consensus_decode(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_merkle_proof_single() {
+        let t0 = [7u8; 32];
+        let proof = MerkleProof {
+            num_transactions: 1,
+            hashes: vec![t0],
+            flag_bits: vec![true],
+        };
+        let (root, matched) = proof.verify().unwrap();
+        assert_eq!(root, t0);
+        assert_eq!(matched, vec![t0]);
+    }
+
+    #[test]
+    fn test_merkle_proof_pair() {
+        let t0 = [1u8; 32];
+        let t1 = [2u8; 32];
+        let expected = calculate_merkle_root(vec![t0, t1]);
+        let proof = MerkleProof {
+            num_transactions: 2,
+            hashes: vec![t0, t1],
+            flag_bits: vec![true, true, true],
+        };
+        let (root, matched) = proof.verify().unwrap();
+        assert_eq!(root, expected);
+        assert_eq!(matched, vec![t0, t1]);
+    }
+
+    #[test]
+    fn test_merkle_proof_rejects_leftover() {
+        // A trailing, unconsumed hash must cause verification to fail.
+        let t0 = [7u8; 32];
+        let proof = MerkleProof {
+            num_transactions: 1,
+            hashes: vec![t0, [9u8; 32]],
+            flag_bits: vec![true],
+        };
+        assert!(proof.verify().is_none());
+    }
+
+    #[test]
+    fn test_merkle_proof_rejects_oversized() {
+        // An attacker-supplied transaction count near u32::MAX must not panic or
+        // loop forever; it simply runs out of data and fails.
+        let proof = MerkleProof {
+            num_transactions: u32::MAX,
+            hashes: vec![],
+            flag_bits: vec![true],
+        };
+        assert!(proof.verify().is_none());
+    }
+
+    #[test]
+    fn test_calc_tree_width_no_overflow() {
+        // A count near u32::MAX at height 31 must not overflow the rounding add.
+        assert_eq!(crate::calc_tree_width(u32::MAX, 31), 2);
+    }
+
+    #[test]
+    fn test_verify_header_chain() {
+        let genesis = crate::create_genesis_block_header(Network::Mainnet);
+        // `create_block_1` keeps its hashes in display (reversed) byte order and
+        // carries the genesis time and nonce; restore block 1's real values.
+        let mut block_1 = crate::create_block_1().header;
+        block_1.prev_blockhash = genesis.calculate_hash();
+        block_1.merkle_root.reverse();
+        block_1.time = 1231469665;
+        block_1.nonce = 2573394689;
+        let params = Params::new(Network::Mainnet);
+        let proof = crate::verify_header_chain(&[genesis, block_1], 0, &params).unwrap();
+        assert_eq!(proof.first_hash, genesis.calculate_hash());
+        assert_eq!(proof.last_hash, block_1.calculate_hash());
+    }
+
+    #[test]
+    fn test_network_max_target() {
+        // The regtest genesis has a target above the mainnet maximum, so it is
+        // only valid under regtest parameters.
+        let header = crate::create_genesis_block_header(Network::Regtest);
+        assert!(header.validate_target(&Params::new(Network::Regtest)));
+        assert!(!header.validate_target(&Params::new(Network::Mainnet)));
+    }
+
+    #[test]
+    fn test_min_difficulty_rule_consulted() {
+        // On networks with the 20-minute rule, a block mined more than 20
+        // minutes after its parent may drop from a harder difficulty to the
+        // pow limit; such a chain must verify.
+        let params = Params::new(Network::Regtest);
+        assert!(params.allow_min_difficulty_blocks);
+
+        // A parent above the minimum difficulty, so that `h1` changes `bits`.
+        let mut h0 = crate::create_genesis_block_header(Network::Regtest);
+        h0.bits = 0x2000ffff;
+        while !h0.validate_target(&params) {
+            h0.nonce += 1;
+        }
+        let mut h1 = Header {
+            version: 1,
+            prev_blockhash: h0.calculate_hash(),
+            merkle_root: h0.merkle_root,
+            time: h0.time + 20 * 60 + 1,
+            bits: params.pow_limit_bits,
+            nonce: 0,
+        };
+        // Grind a nonce that satisfies the (easy) regtest target.
+        while !h1.validate_target(&params) {
+            h1.nonce += 1;
+        }
+
+        let proof = crate::verify_header_chain(&[h0, h1], 0, &params).unwrap();
+        assert_eq!(proof.last_hash, h1.calculate_hash());
+    }
+
+    #[test]
+    fn test_retarget_clamps_timespan() {
+        // A window far shorter than the minimum is clamped, so the target can
+        // drop by at most a factor of four (difficulty rises at most 4x).
+        let old = crate::U256::from_compact(0x1d00ffff);
+        let fast = crate::retarget(old, 0);
+        let quarter = crate::retarget(old, crate::TARGET_TIMESPAN / 4);
+        assert_eq!(fast, quarter);
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        // The genesis target must re-encode to its canonical `bits`.
+        let header = crate::create_genesis_block_header(Network::Mainnet);
+        assert_eq!(header.target().to_compact(), header.bits);
+    }
+
+    #[test]
+    fn test_work_nonzero() {
+        let header = crate::create_genesis_block_header(Network::Mainnet);
+        assert_ne!(header.work(), crate::U256::ZERO);
+    }
+
+    #[test]
+    fn test_wtxid_matches_txid_without_witness() {
+        // A legacy (non-SegWit) transaction has wtxid == txid.
+        let block = crate::create_block_1();
+        let tx = &block.txdata[0];
+        assert_eq!(tx.txid(), tx.wtxid());
+    }
+
+    #[test]
+    fn test_validate_witness_commitment() {
+        // A block of just the coinbase has an all-zero witness root.
+        let reserved = [0u8; 32];
+        let coinbase = segwit_coinbase(reserved, [0u8; 32]);
+        let header = crate::create_genesis_block_header(Network::Mainnet);
+        let block = Block {
+            header,
+            txdata: vec![coinbase],
+        };
+        assert!(block.has_witness_commitment());
+        assert!(block.validate_witness_commitment());
+
+        // Tampering with a byte of the committed value must fail validation.
+        let mut tampered = block;
+        let spk = &mut tampered.txdata[0].output[1].script_pubkey;
+        spk[37] ^= 0x01;
+        assert!(!tampered.validate_witness_commitment());
+    }
+
+    #[test]
+    fn test_wtxid_with_witness_differs_from_txid() {
+        // The witness id differs from the txid once witness data is present.
+        let tx = segwit_coinbase([0u8; 32], [0u8; 32]);
+        assert_ne!(tx.txid(), tx.wtxid());
+    }
+
+    #[test]
+    fn test_transaction_round_trip() {
+        let block = crate::create_block_1();
+        let tx = &block.txdata[0];
+        let mut encoded = vec![];
+        tx.consensus_encode(&mut encoded).unwrap();
+        let decoded = Transaction::consensus_decode(&mut encoded.as_slice()).unwrap();
+        assert_eq!(&decoded, tx);
+    }
+
+    #[test]
+    fn test_witness_transaction_round_trip() {
+        // A witness-bearing transaction must survive the SegWit marker/flag and
+        // per-input witness (de)serialization unchanged.
+        let tx = segwit_coinbase([0u8; 32], [0u8; 32]);
+        let mut encoded = vec![];
+        tx.encode_with_witness(&mut encoded).unwrap();
+        // The serialization must carry the SegWit marker and flag after version.
+        assert_eq!(&encoded[4..6], &[0x00, 0x01]);
+        let decoded = Transaction::consensus_decode(&mut encoded.as_slice()).unwrap();
+        assert_eq!(decoded, tx);
     }
 }
@@ -0,0 +1,48 @@
+use common::create_genesis_block_header;
+use common::{ChainProof, Header, Network};
+use methods::{VERIFY_CHAIN_ELF, VERIFY_CHAIN_ID};
+use risc0_zkvm::{default_prover, ExecutorEnv};
+
+fn main() {
+    // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
+        .init();
+
+    // Prove the first two headers of the chain: the genesis block followed by
+    // block 1, which links back to it.
+    let network = Network::Mainnet;
+    let genesis = create_genesis_block_header(network);
+    // `create_block_1` keeps its hashes in display (reversed) byte order and
+    // carries the genesis time and nonce; restore block 1's real values.
+    let mut block_1 = common::create_block_1().header;
+    block_1.prev_blockhash = genesis.calculate_hash();
+    block_1.merkle_root.reverse();
+    block_1.time = 1231469665;
+    block_1.nonce = 2573394689;
+    let headers: Vec<Header> = vec![genesis, block_1];
+    let start_height: u32 = 0;
+
+    let env = ExecutorEnv::builder()
+        .write(&headers)
+        .unwrap()
+        .write(&start_height)
+        .unwrap()
+        .write(&network)
+        .unwrap()
+        .build()
+        .unwrap();
+
+    // Obtain the default prover.
+    let prover = default_prover();
+
+    // Produce a receipt by proving the specified ELF binary.
+    let receipt = prover.prove(env, VERIFY_CHAIN_ELF).unwrap().receipt;
+
+    let _output: ChainProof = receipt.journal.decode().unwrap();
+
+    // The receipt was verified at the end of proving, but the below code is an
+    // example of how someone else could verify this receipt.
+    receipt.verify(VERIFY_CHAIN_ID).unwrap();
+    println!("Proving successful!");
+}
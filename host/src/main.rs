@@ -1,5 +1,5 @@
 use common::create_genesis_block;
-use common::Block;
+use common::{Block, Network};
 use methods::{VERIFY_ELF, VERIFY_ID};
 use risc0_zkvm::{default_prover, ExecutorEnv};
 
@@ -9,11 +9,14 @@ fn main() {
         .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
         .init();
 
-    let input: Block = create_genesis_block();
+    let network = Network::Mainnet;
+    let input: Block = create_genesis_block(network);
 
     let env = ExecutorEnv::builder()
         .write(&input)
         .unwrap()
+        .write(&network)
+        .unwrap()
         .build()
         .unwrap();
 
@@ -0,0 +1,18 @@
+#![no_main]
+use common::{verify_header_chain, Header, Network, Params};
+use risc0_zkvm::guest::env;
+
+risc0_zkvm::guest::entry!(main);
+
+fn main() {
+    let headers: Vec<Header> = env::read();
+    let start_height: u32 = env::read();
+    let network: Network = env::read();
+    let params = Params::new(network);
+
+    let proof =
+        verify_header_chain(&headers, start_height, &params).expect("invalid header chain");
+
+    // write public output to the journal
+    env::commit(&proof);
+}